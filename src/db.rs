@@ -0,0 +1,179 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, Result};
+
+/// Ordered list of schema migrations, applied once each in order on startup.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        target INTEGER NOT NULL,
+        total_time REAL
+    )",
+    "CREATE TABLE solves (
+        id INTEGER PRIMARY KEY,
+        session_id INTEGER NOT NULL,
+        number INTEGER NOT NULL,
+        time REAL NOT NULL,
+        created_at TEXT NOT NULL
+    )",
+    "ALTER TABLE solves ADD COLUMN penalty TEXT",
+];
+
+/// Only a DNF excludes a solve from the all-time stats; a +2 already has
+/// the penalty seconds folded into its recorded time.
+const DNF_PENALTY: &str = "dnf";
+
+/// A finished (or still running) session as read back from the database.
+pub struct PastSession {
+    pub id: i64,
+    pub started_at: String,
+    pub target: u8,
+    pub total_time: Option<f32>,
+}
+
+/// Handle to the local SQLite file backing solve history.
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        let db = Db { conn };
+        db.run_migrations()?;
+        Ok(db)
+    }
+
+    fn run_migrations(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY)",
+        )?;
+        for (version, migration) in MIGRATIONS.iter().enumerate() {
+            let version = version as i64;
+            let already_applied: bool = self.conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM schema_migrations WHERE version = ?1)",
+                params![version],
+                |row| row.get(0),
+            )?;
+            if already_applied {
+                continue;
+            }
+            self.conn.execute_batch(migration)?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version) VALUES (?1)",
+                params![version],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn start_session(&self, target: u8) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO sessions (started_at, target, total_time) VALUES (?1, ?2, NULL)",
+            params![now_string(), target],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn insert_solve(
+        &self,
+        session_id: i64,
+        number: u8,
+        time: f32,
+        penalty: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO solves (session_id, number, time, created_at, penalty) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session_id, number, time, now_string(), penalty],
+        )?;
+        Ok(())
+    }
+
+    pub fn finish_session(&self, session_id: i64, total_time: f32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET total_time = ?1 WHERE id = ?2",
+            params![total_time, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Fastest single solve ever recorded, across all sessions. DNFs never
+    /// count, however fast their raw elapsed time was.
+    pub fn fastest_solve_ever(&self) -> Result<Option<f32>> {
+        self.conn.query_row(
+            "SELECT MIN(time) FROM solves WHERE penalty IS NULL OR penalty != ?1",
+            params![DNF_PENALTY],
+            |row| row.get(0),
+        )
+    }
+
+    /// Best average (total time / target) among completed sessions.
+    pub fn best_session_average(&self) -> Result<Option<f32>> {
+        self.conn.query_row(
+            "SELECT MIN(total_time / target) FROM sessions WHERE total_time IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Most recent sessions, newest first.
+    pub fn recent_sessions(&self, limit: u32) -> Result<Vec<PastSession>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, started_at, target, total_time FROM sessions ORDER BY id DESC LIMIT ?1",
+        )?;
+        let sessions = stmt
+            .query_map(params![limit], |row| {
+                Ok(PastSession {
+                    id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    target: row.get(2)?,
+                    total_time: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sessions)
+    }
+}
+
+fn now_string() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format_unix_timestamp(since_epoch.as_secs())
+}
+
+/// Formats seconds-since-epoch as a human-readable UTC "YYYY-MM-DD HH:MM:SS"
+/// timestamp, so stored/displayed session times are actually usable.
+fn format_unix_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60,
+    )
+}
+
+/// Converts a day count since the Unix epoch into a proleptic-Gregorian
+/// (year, month, day) civil date. Howard Hinnant's `civil_from_days`
+/// algorithm - chosen over a date-time dependency for the same reason
+/// scramble generation rolls its own PRNG rather than pulling in `rand`.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if month <= 2 { y + 1 } else { y };
+    (year, month, day)
+}
@@ -1,6 +1,6 @@
 use std::io;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
@@ -11,8 +11,15 @@ use ratatui::{
 use rodio::source::SineWave;
 use rodio::{OutputStream, Source};
 
+mod db;
+mod recording;
+mod scramble;
 mod tui;
 
+const DB_PATH: &str = "cube_marathon_timer.db";
+const RECORDING_PATH: &str = "latest_session.rec";
+const REPLAY_SLOW_THRESHOLD: f32 = 10.0;
+
 fn main() -> io::Result<()> {
     let mut terminal = tui::init()?;
     let app_result = App::default().run(&mut terminal);
@@ -20,20 +27,34 @@ fn main() -> io::Result<()> {
     app_result
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    Plus2,
+    Dnf,
+}
+
 #[derive(Debug)]
 pub struct Solve {
     number: u8,
     time: f32,
+    penalty: Option<Penalty>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 enum State {
     Begin,
+    Inspection,
     Running,
     Finished,
+    History,
+    Replay,
 }
 
-#[derive(Debug)]
+/// WCA inspection allows 15 seconds; a solver who starts between 15 and 17
+/// seconds eats a +2 penalty, and beyond 17 seconds the solve is a DNF.
+const INSPECTION_SECS: f32 = 15.0;
+const INSPECTION_DNF_SECS: f32 = 17.0;
+
 pub struct App {
     state: State,
     target: u8,
@@ -43,10 +64,21 @@ pub struct App {
     current_begin: Instant,
     exit: bool,
     solves: Vec<Solve>,
+    db: db::Db,
+    session_id: i64,
+    history: Vec<db::PastSession>,
+    scramble: String,
+    inspection_begin: Instant,
+    pending_penalty: Option<Penalty>,
+    recorder: Option<recording::Recorder>,
+    replay: Option<recording::Recording>,
+    replay_index: usize,
+    replay_begin: Instant,
 }
 
 impl Default for App {
     fn default() -> Self {
+        let db = db::Db::open(DB_PATH).expect("failed to open solve history database");
         App {
             state: State::Begin,
             target: 42,
@@ -56,6 +88,16 @@ impl Default for App {
             current_begin: Instant::now(),
             exit: false,
             solves: vec![],
+            db,
+            session_id: 0,
+            history: vec![],
+            scramble: scramble::generate(),
+            inspection_begin: Instant::now(),
+            pending_penalty: None,
+            recorder: None,
+            replay: None,
+            replay_index: 0,
+            replay_begin: Instant::now(),
         }
     }
 }
@@ -73,6 +115,7 @@ impl App {
     fn render_frame(&self, frame: &mut Frame) {
         match self.state {
             State::Begin => frame.render_widget(self, frame.size()),
+            State::Inspection => frame.render_widget(self, frame.size()),
             State::Running => {
                 let chunks = Layout::default()
                     .direction(Direction::Horizontal)
@@ -91,11 +134,29 @@ impl App {
                 frame.render_widget(self, chunks[0]);
                 render(self, chunks[1], frame.buffer_mut());
             }
+            State::History => frame.render_widget(self, frame.size()),
+            State::Replay => frame.render_widget(self, frame.size()),
         }
     }
 
     /// updates the application's state based on user input
     fn handle_events(&mut self) -> io::Result<()> {
+        // Replay auto-advances in real time, so it can't block on the next
+        // key the way every other state does - poll with a short timeout
+        // instead and fall through to advancing the replay on timeout.
+        if self.state == State::Replay {
+            if event::poll(Duration::from_millis(50))? {
+                if let Event::Key(key_event) = event::read()? {
+                    if key_event.kind == KeyEventKind::Press {
+                        self.handle_key_event(key_event);
+                    }
+                }
+            } else {
+                self.advance_replay();
+            }
+            return Ok(());
+        }
+
         match event::read()? {
             // it's important to check that the event is a key press event as
             // crossterm also emits key release and repeat events on Windows.
@@ -113,9 +174,15 @@ impl App {
                 KeyCode::Char('q') => self.exit(),
                 KeyCode::Right => self.increment_target(),
                 KeyCode::Left => self.decrement_target(),
-                KeyCode::Char(' ') => self.start_timing(),
+                KeyCode::Char(' ') => self.begin_inspection(),
+                KeyCode::Char('h') => self.open_history(),
+                KeyCode::Char('v') => self.open_replay(),
                 _ => {}
             },
+            State::Inspection => match key_event.code {
+                KeyCode::Char('q') => self.exit(),
+                _ => self.start_timing(),
+            },
             State::Running => match key_event.code {
                 KeyCode::Char('q') => self.exit(),
                 _ => self.solve_done(),
@@ -125,14 +192,117 @@ impl App {
                 KeyCode::Char('r') => self.reset(),
                 _ => {}
             },
+            State::History => match key_event.code {
+                KeyCode::Char('q') => self.exit(),
+                KeyCode::Esc | KeyCode::Char('b') => self.state = State::Begin,
+                _ => {}
+            },
+            State::Replay => match key_event.code {
+                KeyCode::Char('q') => self.exit(),
+                KeyCode::Esc | KeyCode::Char('b') => self.state = State::Begin,
+                KeyCode::Char('f') => self.advance_replay_immediately(),
+                KeyCode::Char('n') => self.jump_replay(true, |time| time > REPLAY_SLOW_THRESHOLD),
+                KeyCode::Char('N') => self.jump_replay(false, |time| time > REPLAY_SLOW_THRESHOLD),
+                KeyCode::Char('m') => self.jump_replay(true, |time| time < REPLAY_SLOW_THRESHOLD),
+                KeyCode::Char('M') => self.jump_replay(false, |time| time < REPLAY_SLOW_THRESHOLD),
+                _ => {}
+            },
+        }
+    }
+
+    fn open_history(&mut self) {
+        self.history = self.db.recent_sessions(20).unwrap_or_default();
+        self.state = State::History;
+    }
+
+    fn open_replay(&mut self) {
+        let Ok(recording) = recording::Recording::load(RECORDING_PATH) else {
+            return;
+        };
+        if recording.len() == 0 {
+            return;
+        }
+        self.replay = Some(recording);
+        self.replay_index = 0;
+        self.replay_begin = Instant::now();
+        self.state = State::Replay;
+    }
+
+    /// Moves to the next record once enough real time has passed since
+    /// replay started, the way the recorded session actually played out.
+    fn advance_replay(&mut self) {
+        let Some(recording) = &self.replay else {
+            return;
+        };
+        let Some(next) = recording.get(self.replay_index + 1) else {
+            return;
+        };
+        if self.replay_begin.elapsed().as_secs_f32() >= next.elapsed {
+            self.replay_index += 1;
+        }
+    }
+
+    /// Fast-forwards one frame immediately, ignoring the real-time pacing.
+    fn advance_replay_immediately(&mut self) {
+        if let Some(recording) = &self.replay {
+            if self.replay_index + 1 < recording.len() {
+                self.replay_index += 1;
+            }
         }
     }
 
+    /// Jumps to the next (or, going backward, the previous) recorded solve
+    /// whose time matches `predicate`, then re-anchors the real-time pacing
+    /// so playback continues smoothly from the new position.
+    fn jump_replay(&mut self, forward: bool, predicate: impl Fn(f32) -> bool) {
+        let Some(recording) = &self.replay else {
+            return;
+        };
+        let target = if forward {
+            recording.next_solve_matching(self.replay_index, predicate)
+        } else {
+            recording.previous_solve_matching(self.replay_index, predicate)
+        };
+        let Some(index) = target else {
+            return;
+        };
+        let elapsed = recording.get(index).map(|record| record.elapsed).unwrap_or(0.0);
+        self.replay_index = index;
+        self.replay_begin = Instant::now() - Duration::from_secs_f32(elapsed);
+    }
+
+    fn begin_inspection(&mut self) {
+        self.state = State::Inspection;
+        self.inspection_begin = Instant::now();
+    }
+
     fn start_timing(&mut self) {
+        let inspection_elapsed = self.inspection_begin.elapsed().as_secs_f32();
+        self.pending_penalty = if inspection_elapsed > INSPECTION_DNF_SECS {
+            Some(Penalty::Dnf)
+        } else if inspection_elapsed >= INSPECTION_SECS {
+            Some(Penalty::Plus2)
+        } else {
+            None
+        };
+
         beep();
         self.state = State::Running;
         self.total_begin = Instant::now();
         self.current_begin = Instant::now();
+        self.session_id = self
+            .db
+            .start_session(self.target)
+            .expect("failed to record session start");
+
+        let mut recorder =
+            recording::Recorder::create(RECORDING_PATH).expect("failed to create recording file");
+        recorder
+            .record(recording::Event::SessionStart {
+                target: self.target,
+            })
+            .expect("failed to write recording event");
+        self.recorder = Some(recorder);
     }
 
     fn exit(&mut self) {
@@ -158,17 +328,45 @@ impl App {
             return;
         }
         self.counter += 1;
-        let duration = self.current_begin.elapsed().as_secs_f32();
+        let mut duration = self.current_begin.elapsed().as_secs_f32();
         self.current_begin = Instant::now();
+        let penalty = self.pending_penalty.take();
+        if penalty == Some(Penalty::Plus2) {
+            duration += 2.0;
+        }
         self.solves.push(Solve {
             number: self.counter,
             time: duration,
+            penalty,
         });
+        self.db
+            .insert_solve(self.session_id, self.counter, duration, penalty_db_label(penalty))
+            .expect("failed to record solve");
+        if let Some(recorder) = &mut self.recorder {
+            recorder
+                .record(recording::Event::SolveDone {
+                    number: self.counter,
+                    time: duration,
+                    penalty,
+                })
+                .expect("failed to write recording event");
+        }
+        self.scramble = scramble::generate();
 
         if self.counter == self.target {
-            let duration = self.total_begin.elapsed().as_secs_f32();
+            let duration = self.total_begin.elapsed().as_secs_f32() + penalty_seconds(&self.solves);
             self.total_time = duration;
             self.state = State::Finished;
+            self.db
+                .finish_session(self.session_id, duration)
+                .expect("failed to record session finish");
+            if let Some(recorder) = &mut self.recorder {
+                recorder
+                    .record(recording::Event::SessionFinish {
+                        total_time: duration,
+                    })
+                    .expect("failed to write recording event");
+            }
         }
     }
 
@@ -176,6 +374,8 @@ impl App {
         self.state = State::Begin;
         self.counter = 0;
         self.solves = vec![];
+        self.scramble = scramble::generate();
+        self.pending_penalty = None;
     }
 }
 
@@ -183,8 +383,11 @@ impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let title = match self.state {
             State::Begin => Title::from(" Cube Marathon Timer ".bold()),
+            State::Inspection => Title::from(" Inspection! "),
             State::Running => Title::from(" Session in Progress! Times: "),
             State::Finished => Title::from(" Session done! Times: "),
+            State::History => Title::from(" Solve History ".bold()),
+            State::Replay => Title::from(" Replay "),
         };
         let instructions = match self.state {
             State::Begin => Title::from(Line::from(vec![
@@ -194,6 +397,16 @@ impl Widget for &App {
                 "<Right>".blue().bold(),
                 " Begin ".into(),
                 "<Space>".blue().bold(),
+                " History ".into(),
+                "<H>".blue().bold(),
+                " Replay ".into(),
+                "<V>".blue().bold(),
+                " Quit ".into(),
+                "<Q> ".blue().bold(),
+            ])),
+            State::Inspection => Title::from(Line::from(vec![
+                " Start solving ".into(),
+                "<Any key>".blue().bold(),
                 " Quit ".into(),
                 "<Q> ".blue().bold(),
             ])),
@@ -209,6 +422,24 @@ impl Widget for &App {
                 " Quit ".into(),
                 "<Q> ".blue().bold(),
             ])),
+            State::History => Title::from(Line::from(vec![
+                " Back ".into(),
+                "<B>".blue().bold(),
+                " Quit ".into(),
+                "<Q> ".blue().bold(),
+            ])),
+            State::Replay => Title::from(Line::from(vec![
+                " Fast-forward ".into(),
+                "<F>".blue().bold(),
+                " Next/prev slow solve ".into(),
+                "<N>/<Shift+N>".blue().bold(),
+                " Next/prev sub solve ".into(),
+                "<M>/<Shift+M>".blue().bold(),
+                " Back ".into(),
+                "<B>".blue().bold(),
+                " Quit ".into(),
+                "<Q> ".blue().bold(),
+            ])),
         };
         let block = Block::default()
             .title(title.alignment(Alignment::Center))
@@ -229,34 +460,89 @@ impl Widget for &App {
                     self.target.to_string().yellow(),
                 ]),
             ]),
-            State::Running => Text::from(
-                self.solves
-                    .iter()
-                    .map(|solve| {
-                        Line::from(vec![
-                            "Cube ".into(),
-                            solve.number.to_string().yellow(),
-                            " solved in ".into(),
-                            format!("{:2.2}", solve.time).yellow(),
-                            " sec ".into(),
-                        ])
-                    })
-                    .collect::<Vec<_>>(),
-            ),
-            State::Finished => Text::from(
-                self.solves
-                    .iter()
-                    .map(|solve| {
-                        Line::from(vec![
-                            "Cube ".into(),
-                            solve.number.to_string().yellow(),
-                            " solved in ".into(),
-                            format!("{:2.2}", solve.time).yellow(),
-                            " sec ".into(),
-                        ])
-                    })
-                    .collect::<Vec<_>>(),
-            ),
+            State::Inspection => {
+                let remaining = (INSPECTION_SECS - self.inspection_begin.elapsed().as_secs_f32())
+                    .max(0.0);
+                Text::from(vec![
+                    Line::from(vec![]),
+                    Line::from(vec![]),
+                    Line::from(vec![
+                        "Scramble: ".into(),
+                        self.scramble.clone().yellow(),
+                    ]),
+                    Line::from(vec![]),
+                    Line::from(vec![
+                        "Inspection time left: ".into(),
+                        format!("{:2.1}", remaining).yellow(),
+                        " sec".into(),
+                    ]),
+                ])
+            }
+            State::Running => {
+                let mut lines = vec![
+                    Line::from(vec!["Scramble: ".into(), self.scramble.clone().yellow()]),
+                    Line::from(vec![]),
+                ];
+                lines.extend(self.solves.iter().map(solve_line));
+                Text::from(lines)
+            }
+            State::Finished => {
+                Text::from(self.solves.iter().map(solve_line).collect::<Vec<_>>())
+            }
+            State::History => {
+                let fastest_ever = self.db.fastest_solve_ever().unwrap_or_default();
+                let best_average = self.db.best_session_average().unwrap_or_default();
+                let mut lines = vec![
+                    Line::from(vec![
+                        "All-time fastest single: ".into(),
+                        fastest_ever
+                            .map(|t| format!("{:2.2}", t))
+                            .unwrap_or("N/A".into())
+                            .yellow(),
+                        " sec".into(),
+                    ]),
+                    Line::from(vec![
+                        "Best session average: ".into(),
+                        best_average
+                            .map(|t| format!("{:2.2}", t))
+                            .unwrap_or("N/A".into())
+                            .yellow(),
+                        " sec".into(),
+                    ]),
+                    Line::from(vec![]),
+                ];
+                lines.extend(self.history.iter().map(|session| {
+                    Line::from(vec![
+                        "Session ".into(),
+                        session.id.to_string().yellow(),
+                        " started at ".into(),
+                        session.started_at.clone().yellow(),
+                        " (".into(),
+                        session.target.to_string().yellow(),
+                        " cubes): ".into(),
+                        session
+                            .total_time
+                            .map(time_to_string)
+                            .unwrap_or("in progress".into())
+                            .yellow(),
+                    ])
+                }));
+                Text::from(lines)
+            }
+            State::Replay => {
+                let recording = self.replay.as_ref().expect("replay entered without a recording");
+                let record = recording.get(self.replay_index);
+                Text::from(vec![
+                    Line::from(vec![
+                        "Frame ".into(),
+                        (self.replay_index + 1).to_string().yellow(),
+                        " of ".into(),
+                        recording.len().to_string().yellow(),
+                    ]),
+                    Line::from(vec![]),
+                    Line::from(vec![replay_record_text(record).yellow()]),
+                ])
+            }
         };
 
         if self.state == State::Begin {
@@ -270,28 +556,101 @@ impl Widget for &App {
     }
 }
 
+/// Total seconds added by +2 penalties, already folded into each `Solve.time`
+/// but not into a session's wall-clock `total_time` unless added separately.
+fn penalty_seconds(solves: &[Solve]) -> f32 {
+    solves
+        .iter()
+        .filter(|solve| solve.penalty == Some(Penalty::Plus2))
+        .count() as f32
+        * 2.0
+}
+
 fn calculate_average(app: &App) -> Option<f32> {
-    if app.counter == 0 {
+    let counted: Vec<f32> = app
+        .solves
+        .iter()
+        .filter(|solve| solve.penalty != Some(Penalty::Dnf))
+        .map(|solve| solve.time)
+        .collect();
+    if counted.is_empty() {
         None
     } else {
-        Some(app.solves.iter().map(|solve| solve.time).sum::<f32>() / app.counter as f32)
+        Some(counted.iter().sum::<f32>() / counted.len() as f32)
     }
 }
 
 fn calculate_fastest(app: &App) -> Option<f32> {
     app.solves
         .iter()
+        .filter(|solve| solve.penalty != Some(Penalty::Dnf))
         .map(|solve| solve.time)
         .min_by(|a, b| a.partial_cmp(b).unwrap())
 }
 
 fn calculate_slowest(app: &App) -> Option<f32> {
+    if app.solves.iter().any(|solve| solve.penalty == Some(Penalty::Dnf)) {
+        return Some(f32::INFINITY);
+    }
     app.solves
         .iter()
         .map(|solve| solve.time)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
 }
 
+const AO5_WINDOW: usize = 5;
+const AO12_WINDOW: usize = 12;
+
+/// Canonical "Ao-N" trimmed mean: drop the single fastest and single
+/// slowest solve in the window, average the remaining `n - 2`. A DNF always
+/// counts as the worst time in the window regardless of its raw elapsed
+/// time; two or more DNFs make the whole average a DNF.
+fn trimmed_average(window: &[Solve]) -> Option<f32> {
+    let n = window.len();
+    if n < 3 {
+        return None;
+    }
+    let dnf_count = window
+        .iter()
+        .filter(|solve| solve.penalty == Some(Penalty::Dnf))
+        .count();
+    if dnf_count >= 2 {
+        return Some(f32::INFINITY);
+    }
+    let mut times: Vec<f32> = window
+        .iter()
+        .map(|solve| {
+            if solve.penalty == Some(Penalty::Dnf) {
+                f32::INFINITY
+            } else {
+                solve.time
+            }
+        })
+        .collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let trimmed = &times[1..n - 1];
+    Some(trimmed.iter().sum::<f32>() / trimmed.len() as f32)
+}
+
+/// The current rolling average over the most recent `n` solves, or `None`
+/// until at least `n` solves have been done.
+fn average_of(app: &App, n: usize) -> Option<f32> {
+    if app.solves.len() < n {
+        return None;
+    }
+    trimmed_average(&app.solves[app.solves.len() - n..])
+}
+
+/// The best rolling average of size `n` achieved at any point this session.
+fn best_average_of(app: &App, n: usize) -> Option<f32> {
+    if app.solves.len() < n {
+        return None;
+    }
+    (0..=app.solves.len() - n)
+        .filter_map(|start| trimmed_average(&app.solves[start..start + n]))
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+}
+
 fn predict_total_time(app: &App) -> Option<f32> {
     let avg = calculate_average(app);
     avg.map(|a| a * app.target as f32)
@@ -309,7 +668,7 @@ fn render(app: &App, area: Rect, buf: &mut Buffer) {
         .border_set(border::THICK);
 
     let counter_text = match app.state {
-        State::Begin => panic!(),
+        State::Begin | State::Inspection | State::History | State::Replay => panic!(),
         State::Running => Text::from(vec![
             Line::from(vec![
                 "Current cube: ".into(),
@@ -336,7 +695,23 @@ fn render(app: &App, area: Rect, buf: &mut Buffer) {
             Line::from(vec![
                 "Slowest solve: ".into(),
                 calculate_slowest(&app)
-                    .map(|slow| format!("{:2.2}", slow))
+                    .map(format_time_or_dnf)
+                    .unwrap_or("N/A".into())
+                    .yellow(),
+                " sec".into(),
+            ]),
+            Line::from(vec![
+                "Ao5: ".into(),
+                average_of(app, AO5_WINDOW)
+                    .map(format_time_or_dnf)
+                    .unwrap_or("N/A".into())
+                    .yellow(),
+                " sec".into(),
+            ]),
+            Line::from(vec![
+                "Ao12: ".into(),
+                average_of(app, AO12_WINDOW)
+                    .map(format_time_or_dnf)
                     .unwrap_or("N/A".into())
                     .yellow(),
                 " sec".into(),
@@ -380,7 +755,23 @@ fn render(app: &App, area: Rect, buf: &mut Buffer) {
             Line::from(vec![
                 "Slowest solve: ".into(),
                 calculate_slowest(&app)
-                    .map(|slow| format!("{:2.2}", slow))
+                    .map(format_time_or_dnf)
+                    .unwrap_or("N/A".into())
+                    .yellow(),
+                " sec".into(),
+            ]),
+            Line::from(vec![
+                "Best Ao5: ".into(),
+                best_average_of(app, AO5_WINDOW)
+                    .map(format_time_or_dnf)
+                    .unwrap_or("N/A".into())
+                    .yellow(),
+                " sec".into(),
+            ]),
+            Line::from(vec![
+                "Best Ao12: ".into(),
+                best_average_of(app, AO12_WINDOW)
+                    .map(format_time_or_dnf)
                     .unwrap_or("N/A".into())
                     .yellow(),
                 " sec".into(),
@@ -408,6 +799,66 @@ fn render(app: &App, area: Rect, buf: &mut Buffer) {
         .render(area, buf);
 }
 
+fn penalty_db_label(penalty: Option<Penalty>) -> Option<&'static str> {
+    match penalty {
+        Some(Penalty::Plus2) => Some("plus2"),
+        Some(Penalty::Dnf) => Some("dnf"),
+        None => None,
+    }
+}
+
+fn replay_record_text(record: Option<&recording::Record>) -> String {
+    match record.map(|record| &record.event) {
+        Some(recording::Event::SessionStart { target }) => {
+            format!("Session started, {} cubes to solve", target)
+        }
+        Some(recording::Event::SolveDone {
+            number,
+            time,
+            penalty: Some(Penalty::Plus2),
+        }) => format!("Cube {} solved in {:2.2} sec (+2)", number, time),
+        Some(recording::Event::SolveDone {
+            number,
+            time,
+            penalty: Some(Penalty::Dnf),
+        }) => format!("Cube {} solved in {:2.2} sec (DNF)", number, time),
+        Some(recording::Event::SolveDone {
+            number,
+            time,
+            penalty: None,
+        }) => format!("Cube {} solved in {:2.2} sec", number, time),
+        Some(recording::Event::SessionFinish { total_time }) => {
+            format!("Session finished in {}", time_to_string(*total_time))
+        }
+        None => "End of recording".into(),
+    }
+}
+
+fn format_time_or_dnf(time: f32) -> String {
+    if time.is_infinite() {
+        "DNF".into()
+    } else {
+        format!("{:2.2}", time)
+    }
+}
+
+fn solve_line(solve: &Solve) -> Line<'_> {
+    let penalty_suffix = match solve.penalty {
+        Some(Penalty::Plus2) => " (+2)",
+        Some(Penalty::Dnf) => " (DNF)",
+        None => "",
+    };
+    Line::from(vec![
+        "Cube ".into(),
+        solve.number.to_string().yellow(),
+        " solved in ".into(),
+        format!("{:2.2}", solve.time).yellow(),
+        " sec".into(),
+        penalty_suffix.into(),
+        " ".into(),
+    ])
+}
+
 fn time_to_string(time_secs: f32) -> String {
     let mins = time_secs as u32 / 60;
     let secs = time_secs - mins as f32 * 60.0;
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Instant;
+
+use crate::Penalty;
+
+/// A single timing event that happened during a session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    SessionStart { target: u8 },
+    SolveDone {
+        number: u8,
+        time: f32,
+        penalty: Option<Penalty>,
+    },
+    SessionFinish { total_time: f32 },
+}
+
+/// An `Event` tagged with how many seconds into the session it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub elapsed: f32,
+    pub event: Event,
+}
+
+/// Appends timestamped events to a recording file as a session runs.
+pub struct Recorder {
+    file: File,
+    session_begin: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Recorder {
+            file: File::create(path)?,
+            session_begin: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: Event) -> io::Result<()> {
+        let elapsed = self.session_begin.elapsed().as_secs_f32();
+        writeln!(self.file, "{}", encode(elapsed, &event))
+    }
+}
+
+fn encode(elapsed: f32, event: &Event) -> String {
+    match event {
+        Event::SessionStart { target } => format!("{:.3}\tstart\t{}", elapsed, target),
+        Event::SolveDone {
+            number,
+            time,
+            penalty,
+        } => format!(
+            "{:.3}\tsolve\t{}\t{:.3}\t{}",
+            elapsed,
+            number,
+            time,
+            encode_penalty(*penalty)
+        ),
+        Event::SessionFinish { total_time } => format!("{:.3}\tfinish\t{:.3}", elapsed, total_time),
+    }
+}
+
+fn encode_penalty(penalty: Option<Penalty>) -> &'static str {
+    match penalty {
+        Some(Penalty::Plus2) => "plus2",
+        Some(Penalty::Dnf) => "dnf",
+        None => "-",
+    }
+}
+
+fn decode_penalty(label: &str) -> Option<Penalty> {
+    match label {
+        "plus2" => Some(Penalty::Plus2),
+        "dnf" => Some(Penalty::Dnf),
+        _ => None,
+    }
+}
+
+fn decode(line: &str) -> Option<Record> {
+    let mut parts = line.split('\t');
+    let elapsed: f32 = parts.next()?.parse().ok()?;
+    let event = match parts.next()? {
+        "start" => Event::SessionStart {
+            target: parts.next()?.parse().ok()?,
+        },
+        "solve" => Event::SolveDone {
+            number: parts.next()?.parse().ok()?,
+            time: parts.next()?.parse().ok()?,
+            penalty: decode_penalty(parts.next()?),
+        },
+        "finish" => Event::SessionFinish {
+            total_time: parts.next()?.parse().ok()?,
+        },
+        _ => return None,
+    };
+    Some(Record { elapsed, event })
+}
+
+/// A loaded recording, replayable and searchable by solve.
+pub struct Recording {
+    records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let records = BufReader::new(File::open(path)?)
+            .lines()
+            .filter_map(|line| line.ok().and_then(|l| decode(&l)))
+            .collect();
+        Ok(Recording { records })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Record> {
+        self.records.get(index)
+    }
+
+    /// Index of the next solve after `from` whose time matches `predicate`,
+    /// searching forward.
+    pub fn next_solve_matching(&self, from: usize, predicate: impl Fn(f32) -> bool) -> Option<usize> {
+        self.records
+            .iter()
+            .enumerate()
+            .skip(from + 1)
+            .filter(|(_, record)| solve_time(record).is_some_and(&predicate))
+            .map(|(index, _)| index)
+            .next()
+    }
+
+    /// Index of the previous solve before `from` whose time matches
+    /// `predicate`, searching backward.
+    pub fn previous_solve_matching(
+        &self,
+        from: usize,
+        predicate: impl Fn(f32) -> bool,
+    ) -> Option<usize> {
+        self.records
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(self.records.len() - from)
+            .filter(|(_, record)| solve_time(record).is_some_and(&predicate))
+            .map(|(index, _)| index)
+            .next()
+    }
+}
+
+fn solve_time(record: &Record) -> Option<f32> {
+    match record.event {
+        Event::SolveDone { time, .. } => Some(time),
+        _ => None,
+    }
+}
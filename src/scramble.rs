@@ -0,0 +1,84 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FACES: [&str; 6] = ["U", "D", "F", "B", "L", "R"];
+const SUFFIXES: [&str; 3] = ["", "'", "2"];
+const SCRAMBLE_LEN: usize = 20;
+
+/// Small self-contained xorshift PRNG, seeded from system time.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos() as u64;
+        Rng {
+            state: if nanos == 0 { 1 } else { nanos },
+        }
+    }
+
+    fn gen(&mut self) -> u64 {
+        let mut s = self.state;
+        s ^= s << 13;
+        s ^= s >> 7;
+        s ^= s << 17;
+        self.state = s;
+        s
+    }
+
+    fn gen_range(&mut self, a: usize, b: usize) -> usize {
+        a + (self.gen() as usize) % (b - a)
+    }
+}
+
+/// Returns the axis a face belongs to, so opposite-face moves can be
+/// recognized as redundant with each other (e.g. U and D both turn U/D
+/// layers relative to the other four).
+fn axis(face: &str) -> u8 {
+    match face {
+        "U" | "D" => 0,
+        "F" | "B" => 1,
+        "L" | "R" => 2,
+        _ => unreachable!(),
+    }
+}
+
+/// Generates a WCA-style 3x3 scramble: ~20 moves, never repeating the
+/// immediately preceding face, and never adding a third move in a row on
+/// the same axis (two commuting opposite-face moves are allowed, a third
+/// would be redundant with them).
+pub fn generate() -> String {
+    let mut rng = Rng::new();
+    let mut moves: Vec<&'static str> = vec![];
+
+    while moves.len() < SCRAMBLE_LEN {
+        let face = FACES[rng.gen_range(0, FACES.len())];
+
+        if let Some(&last) = moves.last() {
+            if last == face {
+                continue;
+            }
+        }
+        if moves.len() >= 2 {
+            let prev_axis = axis(moves[moves.len() - 1]);
+            let prev_prev_axis = axis(moves[moves.len() - 2]);
+            if prev_axis == axis(face) && prev_prev_axis == axis(face) {
+                continue;
+            }
+        }
+
+        moves.push(face);
+    }
+
+    moves
+        .iter()
+        .map(|face| {
+            let suffix = SUFFIXES[rng.gen_range(0, SUFFIXES.len())];
+            format!("{}{}", face, suffix)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}